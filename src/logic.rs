@@ -12,16 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, ops::DerefMut};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::DerefMut,
+};
 
-use actix_web::{http::header::Header, web, HttpRequest, Responder};
+use actix_web::{http::header::Header, web, Either, HttpRequest, HttpResponse, Responder};
 use actix_web_httpauth::headers::authorization::{Authorization, Basic};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use eyre::Result;
-use rusqlite::{types::Value, Connection, ToSql, Transaction};
+use futures_util::stream;
+use rusqlite::{params, types::Value, Connection, ToSql, Transaction};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use uuid::Uuid;
 
 use crate::{
-    auth::process_auth,
+    auth::{process_auth, process_auth_jwt, resolve_scopes},
     commons::{check_stored_stmt, prepend_colon, NamedParamsContainer},
     db_config::{AuthMode, DbConfig},
     main_config::Db,
@@ -39,13 +46,46 @@ fn val_db2val_json(val: Value) -> JsonValue {
     }
 }
 
+/// Typed counterpart of [`val_db2val_json`]: every cell becomes a tagged object
+/// that preserves its SQLite storage class, so INTEGER/REAL are never conflated
+/// and BLOBs round-trip losslessly as base64 instead of a JSON integer array.
+fn val_db2val_json_typed(val: Value) -> JsonValue {
+    match val {
+        Value::Null => json!({ "type": "null" }),
+        Value::Integer(v) => json!({ "type": "integer", "value": v }),
+        Value::Real(v) => json!({ "type": "real", "value": v }),
+        Value::Text(v) => json!({ "type": "text", "value": v }),
+        Value::Blob(v) => json!({ "type": "blob", "value": BASE64.encode(v) }),
+    }
+}
+
+/// Decodes a tagged input value (the symmetric form emitted by
+/// [`val_db2val_json_typed`]) back into a `rusqlite` `Value`, decoding base64
+/// blobs to bytes. Returns `None` when the value is not a well-formed tag, so
+/// the caller can fall back to the default untyped binding.
+fn val_json_typed2val_db(v: &JsonValue) -> Option<Value> {
+    let obj = v.as_object()?;
+    match obj.get("type")?.as_str()? {
+        "null" => Some(Value::Null),
+        "integer" => Some(Value::Integer(obj.get("value")?.as_i64()?)),
+        "real" => Some(Value::Real(obj.get("value")?.as_f64()?)),
+        "text" => Some(Value::Text(obj.get("value")?.as_str()?.to_string())),
+        "blob" => BASE64.decode(obj.get("value")?.as_str()?).ok().map(Value::Blob),
+        _ => None,
+    }
+}
+
 // adapted from serde-rusqlite, https://github.com/twistedfall/serde_rusqlite/blob/master/LICENSE
-fn calc_named_params(params: &JsonMap<String, JsonValue>) -> NamedParamsContainer {
+fn calc_named_params(params: &JsonMap<String, JsonValue>, typed: bool) -> NamedParamsContainer {
     let mut named_params: Vec<(String, Box<dyn ToSql>)> = Vec::new();
 
-    params
-        .iter()
-        .for_each(|(k, v)| named_params.push((prepend_colon(k), Box::new(v.to_owned()))));
+    params.iter().for_each(|(k, v)| {
+        let bound: Box<dyn ToSql> = match typed.then(|| val_json_typed2val_db(v)).flatten() {
+            Some(val) => Box::new(val),
+            None => Box::new(v.to_owned()),
+        };
+        named_params.push((prepend_colon(k), bound));
+    });
 
     NamedParamsContainer::from(named_params)
 }
@@ -55,6 +95,7 @@ fn do_query(
     tx: &Transaction,
     sql: &str,
     values: &Option<JsonValue>,
+    typed: bool,
 ) -> Result<(Option<Vec<JsonValue>>, Option<usize>, Option<Vec<usize>>)> {
     let mut stmt = tx.prepare(sql)?;
     let column_names: Vec<String> = stmt
@@ -65,7 +106,7 @@ fn do_query(
     let mut rows = match values {
         Some(p) => {
             let map = p.as_object().unwrap();
-            stmt.query(calc_named_params(map).slice().as_slice())?
+            stmt.query(calc_named_params(map, typed).slice().as_slice())?
         }
         None => stmt.query([])?,
     };
@@ -74,19 +115,92 @@ fn do_query(
         let mut map: JsonMap<String, JsonValue> = JsonMap::new();
         for (i, col_name) in column_names.iter().enumerate() {
             let value: Value = row.get_unwrap(i);
-            map.insert(col_name.to_string(), val_db2val_json(value));
+            let encoded = if typed {
+                val_db2val_json_typed(value)
+            } else {
+                val_db2val_json(value)
+            };
+            map.insert(col_name.to_string(), encoded);
         }
         response.push(JsonValue::Object(map));
     }
     Ok((Some(response), None, None))
 }
 
+/// One frame of a streamed query response. Each emitted row becomes a `row`
+/// event, a terminal `end` event carries the row count and commit status, and
+/// an `error` event carries the SQL failure when the cursor aborts early.
+enum SseEvent {
+    Row(JsonValue),
+    End { rows: usize, committed: bool },
+    Error(String),
+}
+
+impl SseEvent {
+    fn render(&self) -> web::Bytes {
+        let (event, data) = match self {
+            SseEvent::Row(v) => ("row", v.to_string()),
+            SseEvent::End { rows, committed } => {
+                ("end", json!({ "rows": rows, "committed": committed }).to_string())
+            }
+            SseEvent::Error(msg) => ("error", json!({ "error": msg }).to_string()),
+        };
+        web::Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
+    }
+}
+
+/// Streaming counterpart of [`do_query`]: instead of collecting every row into
+/// a `Vec`, each row is handed to `emit` as it leaves the cursor; draining stops
+/// early as soon as `emit` returns `false`. The caller is responsible for
+/// keeping the connection guard alive until this returns, i.e. until the cursor
+/// has been drained. Returns the number of rows emitted.
+fn do_query_stream(
+    tx: &Transaction,
+    sql: &str,
+    values: &Option<JsonValue>,
+    typed: bool,
+    emit: &mut impl FnMut(SseEvent) -> bool,
+) -> Result<usize> {
+    let mut stmt = tx.prepare(sql)?;
+    let column_names: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .map(|cn| cn.to_string())
+        .collect();
+    let mut rows = match values {
+        Some(p) => {
+            let map = p.as_object().unwrap();
+            stmt.query(calc_named_params(map, typed).slice().as_slice())?
+        }
+        None => stmt.query([])?,
+    };
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        let mut map: JsonMap<String, JsonValue> = JsonMap::new();
+        for (i, col_name) in column_names.iter().enumerate() {
+            let value: Value = row.get_unwrap(i);
+            let encoded = if typed {
+                val_db2val_json_typed(value)
+            } else {
+                val_db2val_json(value)
+            };
+            map.insert(col_name.to_string(), encoded);
+        }
+        if !emit(SseEvent::Row(JsonValue::Object(map))) {
+            break;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
 #[allow(clippy::type_complexity)]
 fn do_statement(
     tx: &Transaction,
     sql: &str,
     values: &Option<JsonValue>,
     values_batch: &Option<Vec<JsonValue>>,
+    typed: bool,
 ) -> Result<(Option<Vec<JsonValue>>, Option<usize>, Option<Vec<usize>>)> {
     if values.is_some() && values_batch.is_some() {
         return Err(eyre!(
@@ -99,7 +213,7 @@ fn do_statement(
         (None, Some(changed_rows), None)
     } else if values.is_some() {
         let map = values.as_ref().unwrap().as_object().unwrap();
-        let changed_rows = tx.execute(sql, calc_named_params(map).slice().as_slice())?;
+        let changed_rows = tx.execute(sql, calc_named_params(map, typed).slice().as_slice())?;
         (None, Some(changed_rows), None)
     } else {
         // values_batch.is_some()
@@ -107,37 +221,90 @@ fn do_statement(
         let mut ret = vec![];
         for p in values_batch.as_ref().unwrap() {
             let map = p.as_object().unwrap();
-            let changed_rows = stmt.execute(calc_named_params(map).slice().as_slice())?;
+            let changed_rows = stmt.execute(calc_named_params(map, typed).slice().as_slice())?;
             ret.push(changed_rows);
         }
         (None, None, Some(ret))
     })
 }
 
+/// Runs the configured authorization check for a request. `HttpBasic` and
+/// inline credentials go through `process_auth` as before; `Jwt` validates the
+/// `Authorization: Bearer` token and maps its claims through `process_auth_jwt`.
+/// On success it returns the scopes granted to the resolved principal (empty
+/// when the db is unprotected); `None` means the principal is not authorized.
+fn authorize(
+    conn: &mut Connection,
+    http_req: &req_res::Request,
+    dbconf: &DbConfig,
+    auth_header: &Option<Authorization<Basic>>,
+    bearer: &Option<String>,
+) -> Option<HashSet<String>> {
+    match dbconf.auth.as_ref() {
+        None => Some(HashSet::new()),
+        Some(auth) => {
+            let ok = match auth.mode {
+                AuthMode::Jwt => process_auth_jwt(auth, conn, bearer.as_deref()),
+                _ => process_auth(auth, conn, &http_req.credentials, auth_header),
+            };
+            ok.then(|| resolve_scopes(auth, &http_req.credentials, bearer.as_deref()))
+        }
+    }
+}
+
+/// Resolves the scope a given transaction item requires, given the db's scope
+/// rules. A rule keyed by the stored-statement name takes precedence; otherwise
+/// the item falls back to the verb rule (`read` for queries, `write` for
+/// statements). Verb rules live under the reserved `verb:` namespace so a stored
+/// statement literally named `read`/`write` can't shadow (or be shadowed by) a
+/// verb rule. Returns `None` when no rule applies and the item is unrestricted.
+fn required_scope<'a>(dbconf: &'a DbConfig, name: &str, is_query: bool) -> Option<&'a str> {
+    required_scope_in(dbconf.scope_rules.as_ref(), name, is_query)
+}
+
+/// Core of [`required_scope`], taking the rules map directly so it can be
+/// exercised without constructing a full `DbConfig`.
+fn required_scope_in<'a>(
+    rules: Option<&'a HashMap<String, String>>,
+    name: &str,
+    is_query: bool,
+) -> Option<&'a str> {
+    let rules = rules?;
+    if let Some(scope) = rules.get(name) {
+        return Some(scope.as_str());
+    }
+    let verb = if is_query { "verb:read" } else { "verb:write" };
+    rules.get(verb).map(|s| s.as_str())
+}
+
 fn process(
     conn: &mut Connection,
     http_req: web::Json<req_res::Request>,
     stored_statements: &HashMap<String, String>,
     dbconf: &DbConfig,
     auth_header: &Option<Authorization<Basic>>,
+    bearer: &Option<String>,
 ) -> Result<Response> {
-    if dbconf.auth.is_some()
-        && !process_auth(
-            dbconf.auth.as_ref().unwrap(),
-            conn,
-            &http_req.credentials,
-            auth_header,
-        )
-    {
-        return Ok(Response::new_err(
-            401,
-            -1,
-            "Authorization failed".to_string(),
-        ));
-    }
+    let granted = match authorize(conn, &http_req, dbconf, auth_header, bearer) {
+        Some(scopes) => scopes,
+        None => {
+            return Ok(Response::new_err(
+                401,
+                -1,
+                "Authorization failed".to_string(),
+            ))
+        }
+    };
 
     let tx = conn.transaction()?;
 
+    // When sync is enabled, every committed mutation is appended to the local
+    // changelog from within this same transaction, so the change record and its
+    // effect land (or roll back) atomically.
+    if dbconf.sync {
+        ensure_changelog(&tx)?;
+    }
+
     let mut results = vec![];
     let mut failed = None;
 
@@ -159,11 +326,33 @@ fn process(
             }
         }
 
+        // Capability check: the item's required scope (by stored-statement name,
+        // falling back to its read/write verb) must be held by the principal.
+        let name = trx_item.query.as_ref().or(trx_item.statement.as_ref()).unwrap();
+        if let Some(scope) = required_scope(dbconf, name, trx_item.query.is_some()) {
+            if !granted.contains(scope) {
+                let msg = format!("forbidden: missing required scope '{scope}'");
+                if trx_item.no_fail {
+                    results.push(ResponseItem {
+                        success: false,
+                        error: Some(msg),
+                        result_set: None,
+                        rows_updated: None,
+                        rows_updated_batch: None,
+                    });
+                    continue;
+                } else {
+                    failed = Some((idx, msg));
+                    break;
+                }
+            }
+        }
+
         let ret = if let Some(query) = &trx_item.query {
             let sql =
                 check_stored_stmt(query, stored_statements, dbconf.use_only_stored_statements);
             match sql {
-                Ok(sql) => do_query(&tx, sql, &trx_item.values),
+                Ok(sql) => do_query(&tx, sql, &trx_item.values, http_req.typed),
                 Err(e) => Result::Err(e),
             }
         } else {
@@ -174,7 +363,28 @@ fn process(
                 dbconf.use_only_stored_statements,
             );
             match sql {
-                Ok(sql) => do_statement(&tx, sql, &trx_item.values, &trx_item.values_batch),
+                Ok(sql) => {
+                    let ret = do_statement(
+                        &tx,
+                        sql,
+                        &trx_item.values,
+                        &trx_item.values_batch,
+                        http_req.typed,
+                    );
+                    // Record the mutation in the changelog only once it has
+                    // actually applied, so failed statements leave no trace.
+                    match (ret, dbconf.sync) {
+                        (Ok(v), true) => record_change(
+                            &tx,
+                            sql,
+                            &trx_item.values,
+                            &trx_item.values_batch,
+                            http_req.typed,
+                        )
+                        .map(|()| v),
+                        (ret, _) => ret,
+                    }
+                }
                 Err(e) => Result::Err(e),
             }
         };
@@ -217,6 +427,273 @@ fn process(
     })
 }
 
+/// A single mutation recorded in (or replayed through) the changelog. The `id`
+/// is the locally-assigned, monotonically increasing transaction id; the `uuid`
+/// identifies the change across instances and is what makes re-push idempotent.
+#[derive(Serialize, Deserialize)]
+struct ChangelogEntry {
+    #[serde(default)]
+    id: i64,
+    uuid: String,
+    sql: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<JsonValue>,
+    /// Whether `params` is in the tagged "typed" form (chunk0-3); replay must
+    /// decode it the same way so typed/binary writes survive replication.
+    #[serde(default)]
+    typed: bool,
+}
+
+#[derive(Deserialize)]
+struct SyncPullRequest {
+    #[serde(default)]
+    last_seen_id: i64,
+}
+
+#[derive(Serialize)]
+struct SyncResponse {
+    entries: Vec<ChangelogEntry>,
+    applied: usize,
+    high_water_mark: i64,
+}
+
+/// Creates the internal changelog table if it does not yet exist. The `uuid`
+/// column is unique so that replaying the same remote change is a no-op.
+fn ensure_changelog(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS _sqliterg_changelog (\
+             id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             uuid TEXT NOT NULL UNIQUE, \
+             sql TEXT NOT NULL, \
+             params TEXT, \
+             typed INTEGER NOT NULL DEFAULT 0\
+         );",
+    )?;
+    Ok(())
+}
+
+/// Appends a just-applied mutation, with its bound parameters, to the changelog
+/// under a freshly generated UUID. The bound values are stored verbatim as JSON
+/// (`values` as an object, `values_batch` as an array) so they can be replayed.
+fn record_change(
+    tx: &Transaction,
+    sql: &str,
+    values: &Option<JsonValue>,
+    values_batch: &Option<Vec<JsonValue>>,
+    typed: bool,
+) -> Result<()> {
+    let params = match (values, values_batch) {
+        (Some(v), _) => Some(v.to_string()),
+        (_, Some(b)) => Some(json!(b).to_string()),
+        _ => None,
+    };
+    tx.execute(
+        "INSERT INTO _sqliterg_changelog (uuid, sql, params, typed) VALUES (?1, ?2, ?3, ?4)",
+        params![Uuid::new_v4().to_string(), sql, params, typed],
+    )?;
+    Ok(())
+}
+
+/// Returns every changelog entry recorded after `last_seen_id`, together with
+/// the current high-water-mark so the caller knows where to resume next time.
+fn sync_pull(conn: &mut Connection, last_seen_id: i64) -> Result<SyncResponse> {
+    let tx = conn.transaction()?;
+    ensure_changelog(&tx)?;
+    let entries = {
+        let mut stmt = tx.prepare(
+            "SELECT id, uuid, sql, params, typed FROM _sqliterg_changelog WHERE id > ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map([last_seen_id], |row| {
+            let raw: Option<String> = row.get(3)?;
+            Ok(ChangelogEntry {
+                id: row.get(0)?,
+                uuid: row.get(1)?,
+                sql: row.get(2)?,
+                params: raw.and_then(|p| serde_json::from_str(&p).ok()),
+                typed: row.get(4)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    let high_water_mark =
+        tx.query_row("SELECT COALESCE(MAX(id), 0) FROM _sqliterg_changelog", [], |r| {
+            r.get(0)
+        })?;
+    tx.commit()?;
+    Ok(SyncResponse {
+        entries,
+        applied: 0,
+        high_water_mark,
+    })
+}
+
+/// Applies a batch of remote changelog entries inside a single transaction.
+/// Entries whose UUID is already present are skipped — this both makes the push
+/// idempotent on retry and filters out an instance's own echoed changes. The
+/// returned high-water-mark reflects the ids assigned locally to the batch.
+fn sync_push(conn: &mut Connection, entries: &[ChangelogEntry]) -> Result<SyncResponse> {
+    let tx = conn.transaction()?;
+    ensure_changelog(&tx)?;
+    let mut applied = 0;
+    for entry in entries {
+        let seen: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM _sqliterg_changelog WHERE uuid = ?1",
+            [&entry.uuid],
+            |r| r.get(0),
+        )?;
+        if seen > 0 {
+            continue;
+        }
+        match &entry.params {
+            Some(JsonValue::Object(map)) => {
+                tx.execute(
+                    &entry.sql,
+                    calc_named_params(map, entry.typed).slice().as_slice(),
+                )?;
+            }
+            Some(JsonValue::Array(batch)) => {
+                let mut stmt = tx.prepare(&entry.sql)?;
+                for p in batch {
+                    let map = p.as_object().unwrap();
+                    stmt.execute(calc_named_params(map, entry.typed).slice().as_slice())?;
+                }
+            }
+            _ => {
+                tx.execute(&entry.sql, [])?;
+            }
+        }
+        tx.execute(
+            "INSERT INTO _sqliterg_changelog (uuid, sql, params, typed) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                entry.uuid,
+                entry.sql,
+                entry.params.as_ref().map(|p| p.to_string()),
+                entry.typed
+            ],
+        )?;
+        applied += 1;
+    }
+    let high_water_mark =
+        tx.query_row("SELECT COALESCE(MAX(id), 0) FROM _sqliterg_changelog", [], |r| {
+            r.get(0)
+        })?;
+    tx.commit()?;
+    Ok(SyncResponse {
+        entries: vec![],
+        applied,
+        high_water_mark,
+    })
+}
+
+/// Status-determining pre-flight for the streaming path, run while the caller
+/// still holds the connection guard: it authorizes the principal and checks the
+/// read scope for the (single) query. The caller guarantees the transaction is
+/// a single query before invoking this. Returning `Ok(())` means the body can
+/// be streamed with a `200`; `Err` carries the error response to send instead
+/// (so real HTTP status codes are preserved before the headers are committed).
+fn stream_preflight(
+    conn: &mut Connection,
+    http_req: &req_res::Request,
+    dbconf: &DbConfig,
+    auth_header: &Option<Authorization<Basic>>,
+    bearer: &Option<String>,
+) -> std::result::Result<(), HttpResponse> {
+    let granted = match authorize(conn, http_req, dbconf, auth_header, bearer) {
+        Some(scopes) => scopes,
+        None => return Err(HttpResponse::Unauthorized().finish()),
+    };
+
+    // Enforce the read scope before any cursor is opened.
+    let query = http_req.transaction[0].query.as_ref().unwrap();
+    if let Some(scope) = required_scope(dbconf, query, true) {
+        if !granted.contains(scope) {
+            return Err(HttpResponse::Forbidden()
+                .body(format!("forbidden: missing required scope '{scope}'")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams a single-query transaction as Server-Sent Events. Pre-flight
+/// (auth/scope) must already have passed. The connection guard is acquired on a
+/// dedicated thread and held there only while the cursor is drained — i.e. for
+/// query-execution time, exactly like the buffered path — then dropped as soon
+/// as the last row is sent. Rendered frames go to an *unbounded* channel so the
+/// drain never blocks on the client: a slow SSE consumer can never hold the DB
+/// mutex open. The tradeoff is that a slow consumer lets rendered frames queue
+/// in memory (bounded by the result-set size, as the buffered path already is),
+/// rather than throttling the producer.
+fn stream_response(
+    http_req: req_res::Request,
+    db_name: String,
+    stored_statements: HashMap<String, String>,
+    dbconf: DbConfig,
+) -> HttpResponse {
+    let (tx, rx) =
+        tokio::sync::mpsc::unbounded_channel::<std::result::Result<web::Bytes, actix_web::Error>>();
+
+    std::thread::spawn(move || {
+        let mut emit = |ev: SseEvent| {
+            // A send error means the client hung up; stop draining the cursor.
+            tx.send(Ok(ev.render())).is_ok()
+        };
+
+        let db_lock = MUTEXES.get().unwrap().get(&db_name).unwrap();
+        let mut guard = db_lock.lock().unwrap();
+        let conn = guard.deref_mut();
+
+        let db_tx = match conn.transaction() {
+            Ok(t) => t,
+            Err(e) => {
+                emit(SseEvent::Error(e.to_string()));
+                return;
+            }
+        };
+
+        let query = http_req.transaction[0].query.as_ref().unwrap();
+        let values = &http_req.transaction[0].values;
+        let sql = check_stored_stmt(query, &stored_statements, dbconf.use_only_stored_statements);
+        let outcome = match sql {
+            Ok(sql) => do_query_stream(&db_tx, sql, values, http_req.typed, &mut emit),
+            Err(e) => Err(e),
+        };
+
+        match outcome {
+            Ok(rows) => {
+                let committed = db_tx.commit().is_ok();
+                emit(SseEvent::End { rows, committed });
+            }
+            Err(err) => {
+                let _ = db_tx.rollback();
+                emit(SseEvent::Error(err.to_string()));
+            }
+        }
+    });
+
+    let body = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(body)
+}
+
+/// Extracts a bearer token from the `Authorization` header. The scheme is
+/// matched case-insensitively (RFC 7235 treats it as case-insensitive, so
+/// `Bearer`, `bearer`, … are all accepted); `None` when absent or another
+/// scheme. Validation of the token happens later, in `process_auth_jwt`.
+fn extract_bearer(req: &HttpRequest) -> Option<String> {
+    let header = req.headers().get("Authorization")?.to_str().ok()?;
+    let (scheme, token) = header.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(token.trim().to_string())
+    } else {
+        None
+    }
+}
+
 pub async fn handler(
     req: HttpRequest,
     body: web::Json<req_res::Request>,
@@ -236,9 +713,273 @@ pub async fn handler(
         None
     };
 
+    // For the Jwt auth mode, pull the raw bearer token out of the Authorization
+    // header; signature/claim validation happens later, in `process_auth_jwt`.
+    let bearer = if (db_conf).conf.auth.is_some()
+        && matches!(db_conf.conf.auth.as_ref().unwrap().mode, AuthMode::Jwt)
+    {
+        extract_bearer(&req)
+    } else {
+        None
+    };
+
+    // A `?stream` flag on the query string selects the SSE path, where rows are
+    // emitted one event at a time instead of being buffered into the response.
+    // Only a single-query transaction can stream; a mixed/statement body with
+    // `?stream` falls back to the ordinary buffered path rather than erroring.
+    let streaming = req
+        .query_string()
+        .split('&')
+        .any(|kv| kv == "stream" || kv.starts_with("stream="));
+    let can_stream = streaming
+        && matches!(
+            body.transaction.as_slice(),
+            [item] if item.query.is_some() && item.statement.is_none()
+        );
+
+    let db_lock = MUTEXES.get().unwrap().get(&db_name.to_string()).unwrap();
+
+    if can_stream {
+        // Run the status-determining checks while holding the guard, then hand
+        // the actual row drain off to a dedicated thread so the mutex is not
+        // held on the request task while rows stream out.
+        let preflight = {
+            let mut db_lock_guard = db_lock.lock().unwrap();
+            stream_preflight(db_lock_guard.deref_mut(), &body, &db_conf.conf, &auth, &bearer)
+        };
+        match preflight {
+            Err(resp) => Either::Right(resp),
+            Ok(()) => Either::Right(stream_response(
+                body.into_inner(),
+                db_name.to_string(),
+                db_conf.stored_statements.clone(),
+                db_conf.conf.clone(),
+            )),
+        }
+    } else {
+        let mut db_lock_guard = db_lock.lock().unwrap();
+        let conn = db_lock_guard.deref_mut();
+        Either::Left(
+            process(
+                conn,
+                body,
+                &db_conf.stored_statements,
+                &db_conf.conf,
+                &auth,
+                &bearer,
+            )
+            .unwrap(),
+        )
+    }
+}
+
+/// Gate for the sync endpoints: the feature must be enabled for the db, and the
+/// caller must clear the same authorization check the main `handler` applies
+/// (basic/bearer pulled from the request headers — the sync wire formats carry
+/// no inline credentials). On success returns the principal's granted scopes so
+/// the caller can apply a verb gate; otherwise the error response to send back.
+fn authorize_sync(
+    req: &HttpRequest,
+    conn: &mut Connection,
+    dbconf: &DbConfig,
+) -> std::result::Result<HashSet<String>, HttpResponse> {
+    if !dbconf.sync {
+        return Err(HttpResponse::NotFound().finish());
+    }
+    let auth = match dbconf.auth.as_ref() {
+        None => return Ok(HashSet::new()),
+        Some(auth) => auth,
+    };
+    let bearer = extract_bearer(req);
+    let authorized = match auth.mode {
+        AuthMode::Jwt => process_auth_jwt(auth, conn, bearer.as_deref()),
+        _ => process_auth(auth, conn, &None, &Authorization::<Basic>::parse(req).ok()),
+    };
+    if authorized {
+        Ok(resolve_scopes(auth, &None, bearer.as_deref()))
+    } else {
+        Err(HttpResponse::Unauthorized().finish())
+    }
+}
+
+/// Enforces the db's read/write verb scope against a set of granted scopes.
+/// `Ok(())` when no verb rule applies or the principal holds the required scope.
+fn require_verb_scope(
+    dbconf: &DbConfig,
+    granted: &HashSet<String>,
+    is_query: bool,
+) -> std::result::Result<(), HttpResponse> {
+    if let Some(scope) = required_scope(dbconf, "", is_query) {
+        if !granted.contains(scope) {
+            return Err(HttpResponse::Forbidden()
+                .body(format!("forbidden: missing required scope '{scope}'")));
+        }
+    }
+    Ok(())
+}
+
+/// Sync "pull" endpoint: hands back every changelog entry recorded after the
+/// caller's last-seen transaction id, plus the current high-water-mark.
+pub async fn pull_handler(
+    req: HttpRequest,
+    body: web::Json<SyncPullRequest>,
+    db_conf: web::Data<Db>,
+    db_name: web::Data<String>,
+) -> impl Responder {
+    let db_lock = MUTEXES.get().unwrap().get(&db_name.to_string()).unwrap();
+    let mut db_lock_guard = db_lock.lock().unwrap();
+    let conn = db_lock_guard.deref_mut();
+
+    let granted = match authorize_sync(&req, conn, &db_conf.conf) {
+        Ok(scopes) => scopes,
+        Err(resp) => return Either::Right(resp),
+    };
+    // Pulling the changelog exposes every recorded mutation, so it needs read.
+    if let Err(resp) = require_verb_scope(&db_conf.conf, &granted, true) {
+        return Either::Right(resp);
+    }
+
+    match sync_pull(conn, body.last_seen_id) {
+        Ok(resp) => Either::Left(web::Json(resp)),
+        Err(e) => Either::Right(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+/// Sync "push" endpoint: replays a batch of remote changelog entries inside a
+/// single transaction (idempotently, filtering self-echoes) and returns the new
+/// high-water-mark along with how many entries were actually applied.
+pub async fn push_handler(
+    req: HttpRequest,
+    body: web::Json<Vec<ChangelogEntry>>,
+    db_conf: web::Data<Db>,
+    db_name: web::Data<String>,
+) -> impl Responder {
     let db_lock = MUTEXES.get().unwrap().get(&db_name.to_string()).unwrap();
     let mut db_lock_guard = db_lock.lock().unwrap();
     let conn = db_lock_guard.deref_mut();
 
-    process(conn, body, &db_conf.stored_statements, &db_conf.conf, &auth).unwrap()
+    let granted = match authorize_sync(&req, conn, &db_conf.conf) {
+        Ok(scopes) => scopes,
+        Err(resp) => return Either::Right(resp),
+    };
+    // Push replays arbitrary recorded SQL verbatim, bypassing
+    // `use_only_stored_statements` and the per-statement scope rules, so it is a
+    // full-trust write operation: gate it behind the db's write scope.
+    if let Err(resp) = require_verb_scope(&db_conf.conf, &granted, false) {
+        return Either::Right(resp);
+    }
+
+    match sync_push(conn, &body) {
+        Ok(resp) => Either::Left(web::Json(resp)),
+        Err(e) => Either::Right(HttpResponse::InternalServerError().body(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use std::collections::HashMap;
+
+    #[test]
+    fn typed_roundtrips_blob_as_base64() {
+        let bytes = vec![0u8, 1, 2, 255, 128];
+        let encoded = val_db2val_json_typed(Value::Blob(bytes.clone()));
+        assert_eq!(encoded["type"], "blob");
+        assert_eq!(val_json_typed2val_db(&encoded).unwrap(), Value::Blob(bytes));
+    }
+
+    #[test]
+    fn typed_keeps_integer_and_real_distinct() {
+        let int = val_db2val_json_typed(Value::Integer(1));
+        let real = val_db2val_json_typed(Value::Real(1.0));
+        assert_eq!(int["type"], "integer");
+        assert_eq!(real["type"], "real");
+        assert_eq!(val_json_typed2val_db(&int).unwrap(), Value::Integer(1));
+        assert_eq!(val_json_typed2val_db(&real).unwrap(), Value::Real(1.0));
+    }
+
+    #[test]
+    fn typed_roundtrips_null_and_text() {
+        let null = val_db2val_json_typed(Value::Null);
+        assert_eq!(val_json_typed2val_db(&null).unwrap(), Value::Null);
+        let text = val_db2val_json_typed(Value::Text("hi".to_string()));
+        assert_eq!(val_json_typed2val_db(&text).unwrap(), Value::Text("hi".to_string()));
+    }
+
+    #[test]
+    fn scope_name_rule_precedes_verb_fallback() {
+        let mut rules = HashMap::new();
+        rules.insert("get_user".to_string(), "users:read".to_string());
+        rules.insert("verb:read".to_string(), "any:read".to_string());
+        assert_eq!(
+            required_scope_in(Some(&rules), "get_user", true),
+            Some("users:read")
+        );
+        assert_eq!(
+            required_scope_in(Some(&rules), "other_stmt", true),
+            Some("any:read")
+        );
+    }
+
+    #[test]
+    fn scope_statement_named_read_does_not_collide_with_verb() {
+        let mut rules = HashMap::new();
+        rules.insert("verb:write".to_string(), "db:write".to_string());
+        // A statement literally named "read" has no name rule and the read verb
+        // is unset, so it stays unrestricted — it must not inherit the write rule.
+        assert_eq!(required_scope_in(Some(&rules), "read", true), None);
+    }
+
+    #[test]
+    fn scope_without_rules_is_unrestricted() {
+        assert_eq!(required_scope_in(None, "anything", false), None);
+    }
+
+    #[test]
+    fn sync_push_is_idempotent_and_filters_echoes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, v TEXT);")
+            .unwrap();
+        let entries = vec![ChangelogEntry {
+            id: 0,
+            uuid: "uuid-1".to_string(),
+            sql: "INSERT INTO t (v) VALUES (:v)".to_string(),
+            params: Some(json!({ "v": "hello" })),
+            typed: false,
+        }];
+
+        let first = sync_push(&mut conn, &entries).unwrap();
+        assert_eq!(first.applied, 1);
+
+        // Re-pushing the same uuid applies nothing (idempotent / self-echo).
+        let second = sync_push(&mut conn, &entries).unwrap();
+        assert_eq!(second.applied, 0);
+        assert_eq!(first.high_water_mark, second.high_water_mark);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM t", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn sync_push_replays_typed_blob_losslessly() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE b (data BLOB);").unwrap();
+        let bytes = vec![1u8, 2, 3, 4, 250];
+        let entries = vec![ChangelogEntry {
+            id: 0,
+            uuid: "uuid-blob".to_string(),
+            sql: "INSERT INTO b (data) VALUES (:data)".to_string(),
+            params: Some(json!({ "data": { "type": "blob", "value": BASE64.encode(&bytes) } })),
+            typed: true,
+        }];
+        sync_push(&mut conn, &entries).unwrap();
+
+        let stored: Vec<u8> = conn
+            .query_row("SELECT data FROM b", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(stored, bytes);
+    }
 }